@@ -1,14 +1,39 @@
 use arc_swap::ArcSwap;
 use axum::{
-    Json, Router, http::StatusCode, response::{Html, IntoResponse, Response}, routing::get
+    extract::Query,
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Json, Router,
 };
-use icalendar::{Calendar, CalendarComponent, Component};
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use icalendar::{Calendar, CalendarComponent, Component, Event, Parameter, Property};
+use regex::Regex;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use rrule::RRuleSet;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tracing::info;
 
+/// Hard cap on the number of occurrences generated for a single recurring
+/// event, so a malformed or COUNT-less RRULE can't blow up a refresh cycle.
+const MAX_RECURRENCE_INSTANCES: usize = 730;
+
+/// Last-known-good body and validators for a single feed, used to make
+/// conditional requests and to fall back to when a feed is unreachable.
+#[derive(Debug, Clone, Default)]
+struct FeedCache {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: String,
+}
+
+type FeedCacheMap = Arc<Mutex<HashMap<String, FeedCache>>>;
+
 #[derive(Debug, Deserialize, Clone)]
 struct CalendarFeed {
     id: String,
@@ -28,15 +53,29 @@ struct Config {
     days_past: i64,
     #[serde(default = "default_days_future")]
     days_future: i64,
+    #[serde(default = "default_timezone")]
+    default_timezone: String,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 struct Rule {
     name: String,
+    #[serde(default)]
+    match_mode: MatchMode,
     conditions: Vec<Condition>,
     actions: Vec<Action>,
 }
 
+/// How a rule's conditions combine: `All` (the historical, implicit
+/// behavior) requires every condition to match; `Any` requires just one.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+enum MatchMode {
+    #[default]
+    All,
+    Any,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 struct Condition {
     field: String,
@@ -47,19 +86,37 @@ struct Condition {
 #[derive(Debug, Deserialize, Clone)]
 enum ConditionOp {
     Contains,
+    NotContains,
+    Equals,
+    StartsWith,
+    EndsWith,
+    Regex,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 struct Action {
+    #[serde(default)]
     field: String,
     op: ActionOp,
+    #[serde(default)]
     value: String,
+    #[serde(default)]
+    pattern: String,
+    #[serde(default)]
+    with: String,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 enum ActionOp {
     Set,
     Prepend,
+    Append,
+    /// Remove the property entirely.
+    Delete,
+    /// Regex substitution on the field's current value: `pattern` -> `with`.
+    Replace,
+    /// Drop the whole event from the merge.
+    Drop,
 }
 
 fn default_port() -> u16 {
@@ -78,10 +135,15 @@ fn default_days_future() -> i64 {
     365 // 365 days in the future
 }
 
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
 // Shared state for cached calendar
 #[derive(Clone)]
 struct AppState {
     cached_calendar: Arc<ArcSwap<Calendar>>,
+    default_tz: Tz,
 }
 
 #[tokio::main]
@@ -99,12 +161,25 @@ async fn main() {
         config.refresh_interval_seconds
     );
 
+    // Resolve the configured default timezone once at startup
+    let default_tz: Tz = config
+        .default_timezone
+        .parse()
+        .expect("Invalid default_timezone in config.toml");
+
     // Create shared state for cached calendar
     let cached_calendar = Arc::new(ArcSwap::from_pointee(Calendar::new()));
     let state = AppState {
         cached_calendar: cached_calendar.clone(),
+        default_tz,
     };
 
+    // Per-feed ETag/Last-Modified validators and last-known-good bodies
+    let feed_cache: FeedCacheMap = Arc::new(Mutex::new(HashMap::new()));
+
+    // Pre-compile every Regex condition/action once, up front
+    let regex_cache = build_regex_cache(&config.rules);
+
     // Spawn background task to refresh calendar periodically
     let refresh_interval = config.refresh_interval_seconds;
     let days_past = config.days_past;
@@ -113,10 +188,13 @@ async fn main() {
         refresh_calendar_loop(
             config.feeds,
             config.rules,
+            regex_cache,
             cached_calendar,
+            feed_cache,
             refresh_interval,
             days_past,
             days_future,
+            default_tz,
         )
         .await;
     });
@@ -126,6 +204,7 @@ async fn main() {
         .route("/", get(serve_index))
         .route("/calendar.ics", get(serve_ical_calendar))
         .route("/calendar.json", get(serve_json_calendar))
+        .route("/freebusy.ics", get(serve_freebusy))
         .with_state(state);
 
     // Start the server
@@ -139,18 +218,34 @@ async fn main() {
     axum::serve(listener, app).await.expect("Server failed");
 }
 
+// Threads the full set of per-refresh config/context through the loop;
+// splitting it into a struct isn't worth it for a single internal callee.
+#[allow(clippy::too_many_arguments)]
 async fn refresh_calendar_loop(
     feeds: Vec<CalendarFeed>,
     rules: Vec<Rule>,
+    regex_cache: RegexCache,
     cached_calendar: Arc<ArcSwap<Calendar>>,
+    feed_cache: FeedCacheMap,
     refresh_interval_seconds: u64,
     days_past: i64,
     days_future: i64,
+    default_tz: Tz,
 ) {
     loop {
         info!("Refreshing calendar cache...");
 
-        match fetch_and_merge_calendars(&feeds, &rules, days_past, days_future).await {
+        match fetch_and_merge_calendars(
+            &feeds,
+            &rules,
+            &regex_cache,
+            &feed_cache,
+            days_past,
+            days_future,
+            default_tz,
+        )
+        .await
+        {
             Ok(merged_ical) => {
                 cached_calendar.store(Arc::new(merged_ical));
                 info!("Calendar cache updated successfully");
@@ -166,6 +261,7 @@ async fn refresh_calendar_loop(
 
 async fn serve_ical_calendar(
     axum::extract::State(state): axum::extract::State<AppState>,
+    Query(params): Query<FilterParams>,
 ) -> Result<impl IntoResponse, AppError> {
     let calendar = state.cached_calendar.load();
 
@@ -173,6 +269,8 @@ async fn serve_ical_calendar(
         return Err(AppError(Box::from("Calendar not yet loaded")));
     }
 
+    let calendar = filter_calendar(&calendar, &params, state.default_tz);
+
     Ok((
         StatusCode::OK,
         [("Content-Type", "text/calendar; charset=utf-8")],
@@ -182,6 +280,7 @@ async fn serve_ical_calendar(
 
 async fn serve_json_calendar(
     axum::extract::State(state): axum::extract::State<AppState>,
+    Query(params): Query<FilterParams>,
 ) -> Result<impl IntoResponse, AppError> {
     let calendar = state.cached_calendar.load();
 
@@ -189,12 +288,234 @@ async fn serve_json_calendar(
         return Err(AppError(Box::from("Calendar not yet loaded")));
     }
 
+    let calendar = filter_calendar(&calendar, &params, state.default_tz);
+
+    Ok((StatusCode::OK, Json(calendar.to_json(state.default_tz))))
+}
+
+async fn serve_freebusy(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Query(params): Query<FreeBusyParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let calendar = state.cached_calendar.load();
+
+    if calendar.is_empty() {
+        return Err(AppError(Box::from("Calendar not yet loaded")));
+    }
+
+    let from =
+        parse_iso_bound(&params.from).ok_or_else(|| AppError(Box::from("Invalid 'from' date")))?;
+    let to = parse_iso_bound(&params.to).ok_or_else(|| AppError(Box::from("Invalid 'to' date")))?;
+
+    let freebusy_ical = compute_freebusy(&calendar, from, to, state.default_tz);
+
     Ok((
         StatusCode::OK,
-        Json(calendar.to_json()),
+        [("Content-Type", "text/calendar; charset=utf-8")],
+        freebusy_ical,
     ))
 }
 
+/// Query parameters accepted by `/freebusy.ics`.
+#[derive(Debug, Deserialize)]
+struct FreeBusyParams {
+    from: String,
+    to: String,
+}
+
+/// Walk the merged calendar, collect every non-transparent event's interval
+/// inside `[from, to]`, merge overlapping/adjacent intervals, and emit a
+/// single `VFREEBUSY` with one `FREEBUSY` property per merged period.
+fn compute_freebusy(
+    calendar: &Calendar,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    default_tz: Tz,
+) -> String {
+    let mut intervals = Vec::new();
+
+    for component in &calendar.components {
+        let CalendarComponent::Event(event) = component else {
+            continue;
+        };
+
+        if event.property_value("TRANSP") == Some("TRANSPARENT") {
+            continue;
+        }
+
+        let Some(start) = resolve_event_instant(event, "DTSTART", default_tz) else {
+            continue;
+        };
+        let all_day = event
+            .property_value("DTSTART")
+            .map(is_all_day_value)
+            .unwrap_or(false);
+        let end = resolve_event_instant(event, "DTEND", default_tz).unwrap_or_else(|| {
+            if all_day {
+                start + chrono::Duration::days(1)
+            } else {
+                start
+            }
+        });
+
+        let busy_start = start.max(from);
+        let busy_end = end.min(to);
+        if busy_start < busy_end {
+            intervals.push((busy_start, busy_end));
+        }
+    }
+
+    let merged = merge_busy_intervals(intervals);
+
+    // The icalendar crate has no VFREEBUSY component type, so this is built
+    // by hand in the same RFC 5545 shape the crate's own Display impl
+    // produces (CRLF line endings, one BEGIN/END pair per component).
+    let mut ics = String::from(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//ical-aggregator//EN\r\nBEGIN:VFREEBUSY\r\n",
+    );
+    ics.push_str(&format!(
+        "DTSTAMP:{}\r\n",
+        Utc::now().format("%Y%m%dT%H%M%SZ")
+    ));
+    ics.push_str(&format!("DTSTART:{}\r\n", from.format("%Y%m%dT%H%M%SZ")));
+    ics.push_str(&format!("DTEND:{}\r\n", to.format("%Y%m%dT%H%M%SZ")));
+    for (start, end) in merged {
+        ics.push_str(&format!(
+            "FREEBUSY:{}/{}\r\n",
+            start.format("%Y%m%dT%H%M%SZ"),
+            end.format("%Y%m%dT%H%M%SZ")
+        ));
+    }
+    ics.push_str("END:VFREEBUSY\r\nEND:VCALENDAR\r\n");
+    ics
+}
+
+/// Sort and fold a list of `[start, end)` intervals into the minimal set of
+/// non-overlapping, non-adjacent busy periods.
+fn merge_busy_intervals(
+    mut intervals: Vec<(DateTime<Utc>, DateTime<Utc>)>,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    intervals.sort_by_key(|(start, _)| *start);
+
+    let mut merged: Vec<(DateTime<Utc>, DateTime<Utc>)> = Vec::new();
+    for (start, end) in intervals {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => {
+                if end > last.1 {
+                    last.1 = end;
+                }
+            }
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Query parameters accepted by `/calendar.ics` and `/calendar.json`. All
+/// are optional; filtering runs over the cached calendar at request time so
+/// it stays cheap and stateless.
+#[derive(Debug, Deserialize)]
+struct FilterParams {
+    source: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    q: Option<String>,
+    tz: Option<String>,
+}
+
+/// Apply `source`/`from`/`to`/`q`/`tz` filters to a cached calendar, without
+/// ever mutating the shared cache the refresh loop writes to.
+fn filter_calendar(calendar: &Calendar, params: &FilterParams, default_tz: Tz) -> Calendar {
+    let from = params.from.as_deref().and_then(parse_iso_bound);
+    let to = params.to.as_deref().and_then(parse_iso_bound);
+    let target_tz: Option<Tz> = params.tz.as_deref().and_then(|tz| tz.parse().ok());
+
+    let mut filtered = Calendar::new();
+    filtered.name(calendar.get_name().unwrap_or("Merged Calendar"));
+    if let Some(description) = calendar.get_description() {
+        filtered.description(description);
+    }
+
+    for component in &calendar.components {
+        let CalendarComponent::Event(event) = component else {
+            continue;
+        };
+
+        if let Some(source) = &params.source {
+            if event.property_value("X-CALENDAR-SOURCE") != Some(source.as_str()) {
+                continue;
+            }
+        }
+
+        if from.is_some() || to.is_some() {
+            let Some(start) = resolve_event_instant(event, "DTSTART", default_tz) else {
+                continue;
+            };
+            if from.is_some_and(|from| start < from) || to.is_some_and(|to| start > to) {
+                continue;
+            }
+        }
+
+        if let Some(q) = &params.q {
+            let q = q.to_lowercase();
+            let summary = event.get_summary().unwrap_or("").to_lowercase();
+            let description = event
+                .property_value("DESCRIPTION")
+                .unwrap_or("")
+                .to_lowercase();
+            if !summary.contains(&q) && !description.contains(&q) {
+                continue;
+            }
+        }
+
+        let mut event = event.clone();
+        if let Some(target_tz) = target_tz {
+            normalize_event_timezone(&mut event, target_tz, default_tz);
+        }
+        filtered.push(event);
+    }
+
+    filtered
+}
+
+/// Parse an `?from=`/`?to=` bound as either a full RFC 3339 timestamp or a
+/// bare `YYYY-MM-DD` date (interpreted as midnight UTC).
+fn parse_iso_bound(s: &str) -> Option<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?;
+    Some(DateTime::<Utc>::from_naive_utc_and_offset(
+        date.and_hms_opt(0, 0, 0)?,
+        Utc,
+    ))
+}
+
+/// Resolve a `DTSTART`/`DTEND` property on an already-merged event to an
+/// absolute instant. No `VTIMEZONE` aliases are available here (the merge
+/// only keeps `VEVENT`s), so a `TZID` resolves straight against the IANA
+/// database.
+fn resolve_event_instant(event: &Event, field: &str, default_tz: Tz) -> Option<DateTime<Utc>> {
+    let property = event.properties().get(field)?;
+    let tzid = property.params().get("TZID").map(|p| p.value());
+    parse_ical_date(property.value(), tzid, &HashMap::new(), default_tz)
+}
+
+/// Convert an event's `DTSTART`/`DTEND` into `target_tz`, re-emitting each
+/// with an explicit `TZID` parameter so clients get the zone they asked for.
+fn normalize_event_timezone(event: &mut Event, target_tz: Tz, default_tz: Tz) {
+    for field in ["DTSTART", "DTEND"] {
+        let Some(instant) = resolve_event_instant(event, field, default_tz) else {
+            continue;
+        };
+        let converted = instant.with_timezone(&target_tz);
+        let property = Property::new(field, &converted.format("%Y%m%dT%H%M%S").to_string())
+            .append_parameter(Parameter::new("TZID", target_tz.name()))
+            .done();
+        event.append_property(property);
+    }
+}
+
 async fn serve_index() -> Html<String> {
     match fs::read_to_string("index.html") {
         Ok(content) => Html(content),
@@ -205,20 +526,34 @@ async fn serve_index() -> Html<String> {
 async fn fetch_and_merge_calendars(
     feeds: &[CalendarFeed],
     rules: &[Rule],
+    regex_cache: &RegexCache,
+    feed_cache: &FeedCacheMap,
     days_past: i64,
     days_future: i64,
+    default_tz: Tz,
 ) -> Result<Calendar, Box<dyn std::error::Error>> {
-    // Fetch all calendars concurrently
+    // Fetch all calendars concurrently, sending conditional headers for any
+    // feed we've already seen a validator for.
     let client = reqwest::Client::new();
     let mut fetch_tasks = Vec::new();
 
     for feed in feeds {
         let client = client.clone();
         let feed = feed.clone();
+        let cached = feed_cache.lock().unwrap().get(&feed.id).cloned();
         let task = tokio::spawn(async move {
-            let response = client.get(&feed.url).send().await?;
-            let text = response.text().await?;
-            Ok::<(String, String), reqwest::Error>((text, feed.id))
+            let fetch_result = fetch_feed(&client, &feed.url, cached.as_ref()).await;
+            match fetch_result {
+                Ok((body, new_cache)) => (feed.id, Some(body), new_cache),
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to fetch feed '{}', falling back to last-known-good data: {}",
+                        feed.id,
+                        e
+                    );
+                    (feed.id, cached.map(|c| c.body), None)
+                }
+            }
         });
         fetch_tasks.push(task);
     }
@@ -234,15 +569,31 @@ async fn fetch_and_merge_calendars(
     // Parse and merge all calendars
     for result in results {
         match result {
-            Ok(Ok((ical_text, calendar_id))) => {
-                if let Err(e) =
-                    merge_calendar_events(&ical_text, &mut merged_calendar, rules, &calendar_id, days_past, days_future)
-                {
+            Ok((calendar_id, Some(ical_text), new_cache)) => {
+                if let Some(new_cache) = new_cache {
+                    feed_cache
+                        .lock()
+                        .unwrap()
+                        .insert(calendar_id.clone(), new_cache);
+                }
+                if let Err(e) = merge_calendar_events(
+                    &ical_text,
+                    &mut merged_calendar,
+                    rules,
+                    regex_cache,
+                    &calendar_id,
+                    days_past,
+                    days_future,
+                    default_tz,
+                ) {
                     tracing::warn!("Failed to parse calendar '{}': {}", calendar_id, e);
                 }
             }
-            Ok(Err(e)) => {
-                tracing::warn!("Failed to fetch calendar: {}", e);
+            Ok((calendar_id, None, _)) => {
+                tracing::warn!(
+                    "No cached data available for unreachable feed '{}', dropping from merge",
+                    calendar_id
+                );
             }
             Err(e) => {
                 tracing::warn!("Task failed: {}", e);
@@ -254,32 +605,105 @@ async fn fetch_and_merge_calendars(
     Ok(merged_calendar)
 }
 
+/// Fetch a single feed, sending `If-None-Match`/`If-Modified-Since` when
+/// `cached` has validators. Returns the body (reused from `cached` on a
+/// `304`) along with the updated cache entry to store, or `None` for the
+/// cache update when nothing changed.
+async fn fetch_feed(
+    client: &reqwest::Client,
+    url: &str,
+    cached: Option<&FeedCache>,
+) -> Result<(String, Option<FeedCache>), reqwest::Error> {
+    let mut request = client.get(url);
+    if let Some(cached) = cached {
+        if let Some(etag) = &cached.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = request.send().await?;
+
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        let body = cached.map(|c| c.body.clone()).unwrap_or_default();
+        return Ok((body, None));
+    }
+
+    // A non-2xx response (a transient 5xx, a 404 from a moved feed, ...) is
+    // not a body we want to cache over the last-known-good data. Surface it
+    // as an error so the caller falls back to `cached` the same way it
+    // already does for a request that failed outright.
+    let response = response.error_for_status()?;
+
+    let etag = response
+        .headers()
+        .get(ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let last_modified = response
+        .headers()
+        .get(LAST_MODIFIED)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let text = response.text().await?;
+    let new_cache = FeedCache {
+        etag,
+        last_modified,
+        body: text.clone(),
+    };
+    Ok((text, Some(new_cache)))
+}
+
+// Same rationale as refresh_calendar_loop above: every argument is
+// independently needed per-call context, not an opportunity to bundle.
+#[allow(clippy::too_many_arguments)]
 fn merge_calendar_events(
     ical_text: &str,
     merged: &mut Calendar,
     rules: &[Rule],
+    regex_cache: &RegexCache,
     calendar_id: &str,
     days_past: i64,
     days_future: i64,
+    default_tz: Tz,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    use chrono::{Utc, Duration};
-    
+    use chrono::Duration;
+
     // Calculate date range
     let now = Utc::now();
     let start_date = now - Duration::days(days_past);
     let end_date = now + Duration::days(days_future);
-    
+
     // Parse the iCal text using the parser
     let parsed: Calendar = ical_text.parse()?;
+    let tz_aliases = collect_timezone_aliases(&parsed);
 
     // Extract events and add them to the merged calendar
     for component in parsed.components {
-        if let CalendarComponent::Event(mut event) = component {
+        if let CalendarComponent::Event(event) = component {
+            if event.property_value("RRULE").is_some() {
+                expand_recurring_event(
+                    &event,
+                    start_date,
+                    end_date,
+                    calendar_id,
+                    rules,
+                    regex_cache,
+                    &tz_aliases,
+                    default_tz,
+                    merged,
+                );
+                continue;
+            }
+
+            let mut event = event;
             // Filter by date range
-            if let Some(dtstart_str) = event.property_value("DTSTART") {
-                // Try to parse the date - handle both DATE and DATE-TIME formats
-                let event_date = parse_ical_date(dtstart_str);
-                
+            if let Some(dtstart) = event.properties().get("DTSTART") {
+                let tzid = dtstart.params().get("TZID").map(|p| p.value());
+                let event_date = parse_ical_date(dtstart.value(), tzid, &tz_aliases, default_tz);
+
                 // Skip events outside the date range
                 if let Some(event_dt) = event_date {
                     if event_dt < start_date || event_dt > end_date {
@@ -287,70 +711,424 @@ fn merge_calendar_events(
                     }
                 }
             }
-            
+
+            // The merged Calendar is what actually gets cached and served;
+            // request-time consumers (filter_calendar, compute_freebusy,
+            // to_json) resolve DTSTART/DTEND with no access to this feed's
+            // VTIMEZONE blocks, so a TZID that only resolves through
+            // tz_aliases needs to be rewritten to its canonical IANA name
+            // now, while the alias table is still in scope.
+            normalize_event_tz_aliases(&mut event, &tz_aliases);
+
             event.add_property("X-CALENDAR-SOURCE", calendar_id);
-            for rule in rules {
-                let mut conditions_met = true;
-                for condition in &rule.conditions {
-                    let field_value = event.property_value(&condition.field).unwrap_or("");
-                    match condition.op {
-                        ConditionOp::Contains => {
-                            if !field_value.contains(&condition.value) {
-                                conditions_met = false;
-                                break;
-                            }
-                        }
-                    }
-                }
-                if conditions_met {
-                    info!(
-                        "Applying rule '{}' to event '{}'",
-                        rule.name,
-                        event.get_summary().unwrap_or("Unnamed Event")
-                    );
-                    for action in &rule.actions {
-                        match action.op {
-                            ActionOp::Set => {
-                                event.add_property(&action.field, &action.value);
-                            }
-                            ActionOp::Prepend => {
-                                let current_value =
-                                    event.property_value(&action.field).unwrap_or("");
-                                let new_value = format!("{}{}", action.value, current_value);
-                                event.add_property(&action.field, &new_value);
-                            }
-                        }
-                    }
-                }
+            if !apply_rules(&mut event, rules, regex_cache) {
+                merged.push(event);
             }
-            merged.push(event);
         }
     }
 
     Ok(())
 }
 
-fn parse_ical_date(date_str: &str) -> Option<chrono::DateTime<chrono::Utc>> {
-    use chrono::{NaiveDate, NaiveDateTime, TimeZone};
-    
-    // Remove TZID parameter if present (e.g., "TZID=America/New_York:20231215T120000")
-    let clean_date = if let Some(colon_pos) = date_str.find(':') {
-        &date_str[colon_pos + 1..]
-    } else {
-        date_str
+/// Rewrite a non-recurring event's `DTSTART`/`DTEND` `TZID` parameter to its
+/// canonical IANA name when it only resolved through `tz_aliases` (the
+/// source feed's own `VTIMEZONE` blocks). The merged `Calendar` is all
+/// request-time consumers (`filter_calendar`, `compute_freebusy`,
+/// `to_json`) ever see, and they have no access to this feed's alias
+/// table, so an unresolvable raw `TZID` like "Eastern Standard Time"
+/// would otherwise silently fail `resolve_event_instant` after the merge.
+fn normalize_event_tz_aliases(event: &mut Event, tz_aliases: &HashMap<String, Tz>) {
+    for field in ["DTSTART", "DTEND"] {
+        let Some(property) = event.properties().get(field) else {
+            continue;
+        };
+        let Some(tzid) = property.params().get("TZID").map(|p| p.value()) else {
+            continue;
+        };
+        if tzid.parse::<Tz>().is_ok() {
+            continue; // already a canonical IANA name, nothing to do
+        }
+        let Some(tz) = tz_aliases.get(tzid).copied() else {
+            continue; // not resolvable either way; leave it as-is
+        };
+        let value = property.value().to_string();
+        let property = Property::new(field, &value)
+            .append_parameter(Parameter::new("TZID", tz.name()))
+            .done();
+        event.append_property(property);
+    }
+}
+
+/// Build a `TZID` -> `Tz` lookup from any inline `VTIMEZONE` blocks in a
+/// parsed calendar. This covers feeds whose `TZID` is a well-known
+/// non-IANA alias (the Windows/CLDR display names Outlook and Exchange
+/// export, e.g. "Eastern Standard Time") via `windows_zone_alias` below.
+/// It does not evaluate a `VTIMEZONE`'s own `TZOFFSETTO`/`RRULE` data, so a
+/// genuinely ad-hoc `TZID` with no recognizable alias still falls through
+/// to `parse_ical_date`'s own best-effort `str::parse::<Tz>()`.
+fn collect_timezone_aliases(parsed: &Calendar) -> HashMap<String, Tz> {
+    let mut aliases = HashMap::new();
+    for component in &parsed.components {
+        if let CalendarComponent::Other(timezone) = component {
+            if let Some(tzid) = timezone.property_value("TZID") {
+                let tz = tzid.parse::<Tz>().ok().or_else(|| windows_zone_alias(tzid));
+                if let Some(tz) = tz {
+                    aliases.insert(tzid.to_string(), tz);
+                }
+            }
+        }
+    }
+    aliases
+}
+
+/// A handful of common non-IANA `TZID`s (Windows/CLDR display names) seen in
+/// calendar exports from Outlook/Exchange, mapped to their IANA equivalent.
+fn windows_zone_alias(tzid: &str) -> Option<Tz> {
+    match tzid {
+        "Eastern Standard Time" => Some(Tz::America__New_York),
+        "Central Standard Time" => Some(Tz::America__Chicago),
+        "Mountain Standard Time" => Some(Tz::America__Denver),
+        "Pacific Standard Time" => Some(Tz::America__Los_Angeles),
+        "GMT Standard Time" => Some(Tz::Europe__London),
+        "W. Europe Standard Time" => Some(Tz::Europe__Berlin),
+        "Romance Standard Time" => Some(Tz::Europe__Paris),
+        "China Standard Time" => Some(Tz::Asia__Shanghai),
+        "Tokyo Standard Time" => Some(Tz::Asia__Tokyo),
+        _ => None,
+    }
+}
+
+/// Expand a `VEVENT` carrying an `RRULE` into concrete occurrences that fall
+/// in `[start_date, end_date]`, pushing one cloned event per occurrence into
+/// `merged`. `RDATE`s are added to the recurrence set and `EXDATE`s are
+/// subtracted before enumeration. Each instance gets a `RECURRENCE-ID` equal
+/// to its occurrence start so downstream consumers can tell instances apart.
+#[allow(clippy::too_many_arguments)]
+fn expand_recurring_event(
+    event: &Event,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    calendar_id: &str,
+    rules: &[Rule],
+    regex_cache: &RegexCache,
+    tz_aliases: &HashMap<String, Tz>,
+    default_tz: Tz,
+    merged: &mut Calendar,
+) {
+    let uid = event.get_uid().unwrap_or("<no-uid>").to_string();
+
+    let Some(dtstart_prop) = event.properties().get("DTSTART") else {
+        tracing::warn!("Recurring event '{}' has no DTSTART, skipping", uid);
+        return;
+    };
+    let Some(rrule_str) = event.property_value("RRULE") else {
+        return;
+    };
+    let dtstart_str = dtstart_prop.value();
+    let dtstart_tzid = dtstart_prop.params().get("TZID").map(|p| p.value());
+    let Some(dtstart) = parse_ical_date(dtstart_str, dtstart_tzid, tz_aliases, default_tz) else {
+        tracing::warn!(
+            "Recurring event '{}' has an unparseable DTSTART, skipping",
+            uid
+        );
+        return;
+    };
+
+    let all_day = is_all_day_value(dtstart_str);
+    let dtend = event
+        .properties()
+        .get("DTEND")
+        .and_then(|p| {
+            let tzid = p.params().get("TZID").map(|t| t.value());
+            parse_ical_date(p.value(), tzid, tz_aliases, default_tz)
+        })
+        .unwrap_or(dtstart);
+    let duration = dtend - dtstart;
+
+    // Resolve the zone DTSTART's wall-clock time is expressed in, so a
+    // BYDAY/BYHOUR rule from a DST-observing zone is evaluated against local
+    // time rather than UTC (occurrences near midnight would otherwise land
+    // on the wrong calendar day and drift by an hour across DST changes).
+    let dtstart_tz = dtstart_tzid.map(|tzid| {
+        tz_aliases
+            .get(tzid)
+            .copied()
+            .unwrap_or_else(|| tzid.parse().unwrap_or(default_tz))
+    });
+
+    // Format a DTSTART/RDATE/EXDATE line, keeping it in the same frame as
+    // DTSTART (its original TZID, or UTC if it had none) so the RRULE parser
+    // sees a consistent recurrence set. The TZID written out is the
+    // resolved zone's own canonical IANA name rather than the raw source
+    // string: rrule's parser looks TZID up itself and knows nothing about
+    // `tz_aliases`, so a non-IANA alias (e.g. "Eastern Standard Time")
+    // would otherwise fail recurrence_text.parse() outright.
+    let format_recurrence_line = |name: &str, instant: DateTime<Utc>| -> String {
+        if all_day {
+            format!("{};VALUE=DATE:{}", name, instant.format("%Y%m%d"))
+        } else if let Some(tz) = dtstart_tz {
+            format!(
+                "{};TZID={}:{}",
+                name,
+                tz.name(),
+                instant.with_timezone(&tz).format("%Y%m%dT%H%M%S")
+            )
+        } else {
+            format!("{}:{}", name, instant.format("%Y%m%dT%H%M%SZ"))
+        }
+    };
+
+    let dtstart_line = format_recurrence_line("DTSTART", dtstart);
+    let mut recurrence_text = format!("{}\nRRULE:{}", dtstart_line, rrule_str);
+    let rdates = event.properties().get("RDATE").into_iter().chain(
+        event.multi_properties().get("RDATE").into_iter().flatten(),
+    );
+    for rdate in rdates {
+        let tzid = rdate.params().get("TZID").map(|p| p.value());
+        if let Some(instant) = parse_ical_date(rdate.value(), tzid, tz_aliases, default_tz) {
+            recurrence_text.push('\n');
+            recurrence_text.push_str(&format_recurrence_line("RDATE", instant));
+        }
+    }
+    let exdates = event.properties().get("EXDATE").into_iter().chain(
+        event.multi_properties().get("EXDATE").into_iter().flatten(),
+    );
+    for exdate in exdates {
+        let tzid = exdate.params().get("TZID").map(|p| p.value());
+        if let Some(instant) = parse_ical_date(exdate.value(), tzid, tz_aliases, default_tz) {
+            recurrence_text.push('\n');
+            recurrence_text.push_str(&format_recurrence_line("EXDATE", instant));
+        }
+    }
+
+    let rrule_set: RRuleSet = match recurrence_text.parse() {
+        Ok(set) => set,
+        Err(e) => {
+            tracing::warn!("Failed to build recurrence set for event '{}': {}", uid, e);
+            return;
+        }
+    };
+
+    // Bound generation to the merge window itself rather than taking the
+    // first N occurrences from DTSTART and filtering afterward: a
+    // long-running, COUNT-less event can easily have more than
+    // MAX_RECURRENCE_INSTANCES occurrences between its original DTSTART and
+    // today, which would otherwise exhaust the cap before reaching the
+    // window and make the event vanish from the merge.
+    let rrule_set = rrule_set
+        .after(rrule::Tz::UTC.from_utc_datetime(&start_date.naive_utc()))
+        .before(rrule::Tz::UTC.from_utc_datetime(&end_date.naive_utc()));
+
+    let result = rrule_set.all(MAX_RECURRENCE_INSTANCES as u16);
+    let occurrences = result.dates;
+    if result.limited {
+        tracing::warn!(
+            "Event '{}' has more than {} recurrence instances in range, truncating",
+            uid,
+            MAX_RECURRENCE_INSTANCES
+        );
+    }
+
+    for occurrence in occurrences {
+        let occurrence_start = occurrence.with_timezone(&Utc);
+        let occurrence_end = occurrence_start + duration;
+
+        let mut instance = event.clone();
+        if all_day {
+            instance.add_property("DTSTART", occurrence_start.format("%Y%m%d").to_string());
+            instance.add_property("DTEND", occurrence_end.format("%Y%m%d").to_string());
+            instance.add_property(
+                "RECURRENCE-ID",
+                occurrence_start.format("%Y%m%d").to_string(),
+            );
+        } else {
+            instance.add_property(
+                "DTSTART",
+                occurrence_start.format("%Y%m%dT%H%M%SZ").to_string(),
+            );
+            instance.add_property("DTEND", occurrence_end.format("%Y%m%dT%H%M%SZ").to_string());
+            instance.add_property(
+                "RECURRENCE-ID",
+                occurrence_start.format("%Y%m%dT%H%M%SZ").to_string(),
+            );
+        }
+
+        instance.add_property("X-CALENDAR-SOURCE", calendar_id);
+        if !apply_rules(&mut instance, rules, regex_cache) {
+            merged.push(instance);
+        }
+    }
+}
+
+/// `true` if an (unparsed) `DTSTART`/`DTEND` value is a bare `DATE` (e.g.
+/// `20240101`) rather than a `DATE-TIME`, so callers can avoid coercing
+/// all-day events to midnight UTC.
+fn is_all_day_value(date_str: &str) -> bool {
+    let value = match date_str.rfind(':') {
+        Some(pos) => &date_str[pos + 1..],
+        None => date_str,
     };
-    
-    // Try parsing as DATE-TIME (e.g., "20231215T120000Z" or "20231215T120000")
-    if let Ok(dt) = NaiveDateTime::parse_from_str(clean_date.trim_end_matches('Z'), "%Y%m%dT%H%M%S") {
-        return Some(chrono::Utc.from_utc_datetime(&dt));
+    value.len() == 8 && value.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Pre-compiled `Regex` conditions/actions, keyed by their pattern string,
+/// built once at startup so events don't recompile the same pattern on
+/// every refresh cycle.
+type RegexCache = HashMap<String, Regex>;
+
+fn build_regex_cache(rules: &[Rule]) -> RegexCache {
+    let mut cache = RegexCache::new();
+    let mut compile = |pattern: &str, rule_name: &str| {
+        if pattern.is_empty() || cache.contains_key(pattern) {
+            return;
+        }
+        match Regex::new(pattern) {
+            Ok(re) => {
+                cache.insert(pattern.to_string(), re);
+            }
+            Err(e) => tracing::warn!("Invalid regex '{}' in rule '{}': {}", pattern, rule_name, e),
+        }
+    };
+
+    for rule in rules {
+        for condition in &rule.conditions {
+            if matches!(condition.op, ConditionOp::Regex) {
+                compile(&condition.value, &rule.name);
+            }
+        }
+        for action in &rule.actions {
+            if matches!(action.op, ActionOp::Replace) {
+                compile(&action.pattern, &rule.name);
+            }
+        }
+    }
+
+    cache
+}
+
+/// Apply every matching rule's actions to `event` in place. Returns `true`
+/// if a `Drop` action fired, meaning the caller should discard the event
+/// instead of merging it.
+fn apply_rules(event: &mut Event, rules: &[Rule], regex_cache: &RegexCache) -> bool {
+    for rule in rules {
+        let conditions_met = match rule.match_mode {
+            MatchMode::All => rule
+                .conditions
+                .iter()
+                .all(|c| condition_matches(event, c, regex_cache)),
+            MatchMode::Any => rule
+                .conditions
+                .iter()
+                .any(|c| condition_matches(event, c, regex_cache)),
+        };
+        if !conditions_met {
+            continue;
+        }
+
+        info!(
+            "Applying rule '{}' to event '{}'",
+            rule.name,
+            event.get_summary().unwrap_or("Unnamed Event")
+        );
+        for action in &rule.actions {
+            match action.op {
+                ActionOp::Set => {
+                    event.add_property(&action.field, &action.value);
+                }
+                ActionOp::Prepend => {
+                    let current_value = event.property_value(&action.field).unwrap_or("");
+                    let new_value = format!("{}{}", action.value, current_value);
+                    event.add_property(&action.field, &new_value);
+                }
+                ActionOp::Append => {
+                    let current_value = event.property_value(&action.field).unwrap_or("");
+                    let new_value = format!("{}{}", current_value, action.value);
+                    event.add_property(&action.field, &new_value);
+                }
+                ActionOp::Delete => {
+                    event.remove_property(&action.field);
+                }
+                ActionOp::Replace => {
+                    let Some(regex) = regex_cache.get(&action.pattern) else {
+                        tracing::warn!(
+                            "Rule '{}' has no compiled regex for pattern '{}', skipping",
+                            rule.name,
+                            action.pattern
+                        );
+                        continue;
+                    };
+                    let current_value = event.property_value(&action.field).unwrap_or("");
+                    let new_value = regex.replace_all(current_value, action.with.as_str());
+                    event.add_property(&action.field, &new_value.into_owned());
+                }
+                ActionOp::Drop => {
+                    info!("Rule '{}' dropped event from the merge", rule.name);
+                    return true;
+                }
+            }
+        }
     }
-    
-    // Try parsing as DATE only (e.g., "20231215")
-    if let Ok(d) = NaiveDate::parse_from_str(clean_date, "%Y%m%d") {
-        return Some(chrono::Utc.from_utc_datetime(&d.and_hms_opt(0, 0, 0)?));
+    false
+}
+
+/// Evaluate a single condition against `event`.
+fn condition_matches(event: &Event, condition: &Condition, regex_cache: &RegexCache) -> bool {
+    let field_value = event.property_value(&condition.field).unwrap_or("");
+    match condition.op {
+        ConditionOp::Contains => field_value.contains(&condition.value),
+        ConditionOp::NotContains => !field_value.contains(&condition.value),
+        ConditionOp::Equals => field_value == condition.value,
+        ConditionOp::StartsWith => field_value.starts_with(&condition.value),
+        ConditionOp::EndsWith => field_value.ends_with(&condition.value),
+        ConditionOp::Regex => regex_cache
+            .get(&condition.value)
+            .is_some_and(|re| re.is_match(field_value)),
     }
-    
-    None
+}
+
+/// Resolve a raw `DTSTART`/`DTEND` value into an absolute instant.
+///
+/// Honors an explicit `tzid` (resolved against `tz_aliases` built from any
+/// inline `VTIMEZONE` blocks, falling back to the IANA database), treats a
+/// trailing `Z` as UTC, and falls back to `default_tz` for a bare floating
+/// `DATE-TIME`. All-day `DATE` values (e.g. `20231215`) are treated as UTC
+/// midnight without being run through a timezone.
+fn parse_ical_date(
+    value: &str,
+    tzid: Option<&str>,
+    tz_aliases: &HashMap<String, Tz>,
+    default_tz: Tz,
+) -> Option<DateTime<Utc>> {
+    use chrono::NaiveDateTime;
+
+    if is_all_day_value(value) {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0)?));
+    }
+
+    if let Some(tzid) = tzid {
+        let tz = tz_aliases
+            .get(tzid)
+            .copied()
+            .or_else(|| tzid.parse::<Tz>().ok())?;
+        let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+        return tz
+            .from_local_datetime(&naive)
+            .single()
+            .map(|dt| dt.with_timezone(&Utc));
+    }
+
+    if let Some(stripped) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(stripped, "%Y%m%dT%H%M%S").ok()?;
+        return Some(Utc.from_utc_datetime(&naive));
+    }
+
+    // Floating local time with no TZID: fall back to the configured default.
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    default_tz
+        .from_local_datetime(&naive)
+        .single()
+        .map(|dt| dt.with_timezone(&Utc))
 }
 
 // Error handling
@@ -377,11 +1155,11 @@ impl IntoResponse for AppError {
 }
 
 trait CalendarExt {
-    fn to_json(&self) -> serde_json::Value;
+    fn to_json(&self, default_tz: Tz) -> serde_json::Value;
 }
 
 impl CalendarExt for Calendar {
-    fn to_json(&self) -> serde_json::Value {
+    fn to_json(&self, default_tz: Tz) -> serde_json::Value {
         let mut events = Vec::new();
         for component in &self.components {
             if let CalendarComponent::Event(event) = component {
@@ -392,19 +1170,19 @@ impl CalendarExt for Calendar {
                         serde_json::Value::String(summary.to_string()),
                     );
                 }
-                if let Some(dtstart) = event.get_start() {
+                if let Some(start) = resolve_event_instant(event, "DTSTART", default_tz) {
                     event_map.insert(
                         "start".to_string(),
-                        serde_json::Value::String(dtstart.to_property("0xDEADBEEF").value().to_string()),
+                        serde_json::Value::String(start.to_rfc3339()),
                     );
                 }
-                if let Some(dtend) = event.get_end() {
+                if let Some(end) = resolve_event_instant(event, "DTEND", default_tz) {
                     event_map.insert(
                         "end".to_string(),
-                        serde_json::Value::String(dtend.to_property("0xDEADBEEF").value().to_string()),
+                        serde_json::Value::String(end.to_rfc3339()),
                     );
                 }
-                for (_, property) in event.properties() {
+                for property in event.properties().values() {
                     event_map.insert(
                         property.key().to_string(),
                         serde_json::Value::String(property.value().to_string()),
@@ -416,3 +1194,513 @@ impl CalendarExt for Calendar {
         events.into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ical_date_honors_trailing_z_as_utc() {
+        let parsed = parse_ical_date("20240101T090000Z", None, &HashMap::new(), Tz::UTC);
+        assert_eq!(
+            parsed,
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn parse_ical_date_honors_explicit_tzid() {
+        let parsed = parse_ical_date(
+            "20240101T090000",
+            Some("America/New_York"),
+            &HashMap::new(),
+            Tz::UTC,
+        );
+        // 09:00 Eastern Standard Time (UTC-5) is 14:00 UTC.
+        assert_eq!(
+            parsed,
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 14, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn parse_ical_date_resolves_tzid_through_aliases() {
+        let mut aliases = HashMap::new();
+        aliases.insert("Eastern Standard Time".to_string(), Tz::America__New_York);
+        let parsed = parse_ical_date(
+            "20240101T090000",
+            Some("Eastern Standard Time"),
+            &aliases,
+            Tz::UTC,
+        );
+        assert_eq!(
+            parsed,
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 14, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn parse_ical_date_falls_back_to_default_tz_for_floating_time() {
+        let parsed = parse_ical_date(
+            "20240101T090000",
+            None,
+            &HashMap::new(),
+            Tz::America__Chicago,
+        );
+        // 09:00 floating, assumed Central Standard Time (UTC-6), is 15:00 UTC.
+        assert_eq!(
+            parsed,
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 15, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn parse_ical_date_treats_bare_date_as_utc_midnight() {
+        let parsed = parse_ical_date("20240101", None, &HashMap::new(), Tz::America__Chicago);
+        assert_eq!(
+            parsed,
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn is_all_day_value_detects_bare_dates() {
+        assert!(is_all_day_value("20240101"));
+        assert!(is_all_day_value("DTSTART;VALUE=DATE:20240101"));
+        assert!(!is_all_day_value("20240101T090000"));
+        assert!(!is_all_day_value("20240101T090000Z"));
+    }
+
+    #[test]
+    fn merge_busy_intervals_joins_overlapping_and_adjacent() {
+        let t = |h: u32| Utc.with_ymd_and_hms(2024, 1, 1, h, 0, 0).unwrap();
+        let merged = merge_busy_intervals(vec![
+            (t(9), t(10)),
+            (t(10), t(11)), // adjacent to the first
+            (t(10), t(12)), // overlaps the first two
+            (t(14), t(15)), // disjoint
+        ]);
+        assert_eq!(merged, vec![(t(9), t(12)), (t(14), t(15))]);
+    }
+
+    #[test]
+    fn merge_busy_intervals_handles_unsorted_input() {
+        let t = |h: u32| Utc.with_ymd_and_hms(2024, 1, 1, h, 0, 0).unwrap();
+        let merged = merge_busy_intervals(vec![(t(14), t(15)), (t(9), t(10))]);
+        assert_eq!(merged, vec![(t(9), t(10)), (t(14), t(15))]);
+    }
+
+    #[test]
+    fn merge_busy_intervals_empty_input() {
+        assert_eq!(merge_busy_intervals(vec![]), vec![]);
+    }
+
+    fn daily_rrule_event(dtstart: &str, exdate: Option<&str>) -> Event {
+        let mut event = Event::new();
+        event.add_property("UID", "recurring-1");
+        event.add_property("SUMMARY", "Daily standup");
+        event.add_property("DTSTART", dtstart);
+        event.add_property("DTEND", dtstart);
+        event.add_property("RRULE", "FREQ=DAILY");
+        if let Some(exdate) = exdate {
+            event.add_property("EXDATE", exdate);
+        }
+        event.done()
+    }
+
+    #[test]
+    fn expand_recurring_event_bounds_occurrences_to_the_merge_window() {
+        let event = daily_rrule_event("20200101T090000Z", None);
+        let start_date = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let end_date = Utc.with_ymd_and_hms(2024, 6, 5, 0, 0, 0).unwrap();
+        let mut merged = Calendar::new();
+
+        expand_recurring_event(
+            &event,
+            start_date,
+            end_date,
+            "cal-1",
+            &[],
+            &RegexCache::new(),
+            &HashMap::new(),
+            Tz::UTC,
+            &mut merged,
+        );
+
+        let starts: Vec<DateTime<Utc>> = merged
+            .components
+            .iter()
+            .filter_map(|c| match c {
+                CalendarComponent::Event(event) => resolve_event_instant(event, "DTSTART", Tz::UTC),
+                _ => None,
+            })
+            .collect();
+
+        // A DTSTART from years before the window, with no COUNT/UNTIL, must
+        // still produce occurrences landing inside [start_date, end_date)
+        // rather than being skipped (the original bug this fixes) or
+        // exhausting MAX_RECURRENCE_INSTANCES before ever reaching the window.
+        assert_eq!(starts.len(), 4);
+        for start in &starts {
+            assert!(*start >= start_date && *start < end_date);
+        }
+    }
+
+    #[test]
+    fn expand_recurring_event_honors_exdate() {
+        let event = daily_rrule_event("20240601T090000Z", Some("20240602T090000Z"));
+        let start_date = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let end_date = Utc.with_ymd_and_hms(2024, 6, 4, 0, 0, 0).unwrap();
+        let mut merged = Calendar::new();
+
+        expand_recurring_event(
+            &event,
+            start_date,
+            end_date,
+            "cal-1",
+            &[],
+            &RegexCache::new(),
+            &HashMap::new(),
+            Tz::UTC,
+            &mut merged,
+        );
+
+        let starts: Vec<DateTime<Utc>> = merged
+            .components
+            .iter()
+            .filter_map(|c| match c {
+                CalendarComponent::Event(event) => resolve_event_instant(event, "DTSTART", Tz::UTC),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(starts.len(), 2);
+        let excluded = Utc.with_ymd_and_hms(2024, 6, 2, 9, 0, 0).unwrap();
+        assert!(!starts.contains(&excluded));
+    }
+
+    #[test]
+    fn expand_recurring_event_applies_rules_and_can_drop_instances() {
+        let event = daily_rrule_event("20240601T090000Z", None);
+        let start_date = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let end_date = Utc.with_ymd_and_hms(2024, 6, 3, 0, 0, 0).unwrap();
+        let mut merged = Calendar::new();
+        let rules = vec![Rule {
+            name: "drop standups".to_string(),
+            match_mode: MatchMode::All,
+            conditions: vec![Condition {
+                field: "SUMMARY".to_string(),
+                op: ConditionOp::Contains,
+                value: "standup".to_string(),
+            }],
+            actions: vec![Action {
+                field: String::new(),
+                op: ActionOp::Drop,
+                value: String::new(),
+                pattern: String::new(),
+                with: String::new(),
+            }],
+        }];
+
+        expand_recurring_event(
+            &event,
+            start_date,
+            end_date,
+            "cal-1",
+            &rules,
+            &build_regex_cache(&rules),
+            &HashMap::new(),
+            Tz::UTC,
+            &mut merged,
+        );
+
+        assert!(merged.components.is_empty());
+    }
+
+    fn calendar_with_events(events: Vec<Event>) -> Calendar {
+        let mut calendar = Calendar::new();
+        for event in events {
+            calendar.push(event);
+        }
+        calendar
+    }
+
+    fn event(uid: &str, source: &str, dtstart: &str, summary: &str, description: &str) -> Event {
+        Event::new()
+            .uid(uid)
+            .add_property("X-CALENDAR-SOURCE", source)
+            .add_property("DTSTART", dtstart)
+            .add_property("DTEND", dtstart)
+            .summary(summary)
+            .description(description)
+            .done()
+    }
+
+    fn filtered_uids(calendar: &Calendar, params: FilterParams) -> Vec<String> {
+        filter_calendar(calendar, &params, Tz::UTC)
+            .components
+            .iter()
+            .filter_map(|c| match c {
+                CalendarComponent::Event(event) => event.get_uid().map(str::to_string),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn filter_calendar_by_source() {
+        let calendar = calendar_with_events(vec![
+            event("a", "work", "20240601T090000Z", "Standup", ""),
+            event("b", "personal", "20240601T100000Z", "Gym", ""),
+        ]);
+        let params = FilterParams {
+            source: Some("work".to_string()),
+            from: None,
+            to: None,
+            q: None,
+            tz: None,
+        };
+        assert_eq!(filtered_uids(&calendar, params), vec!["a"]);
+    }
+
+    #[test]
+    fn filter_calendar_by_date_range() {
+        let calendar = calendar_with_events(vec![
+            event("a", "work", "20240601T090000Z", "Standup", ""),
+            event("b", "work", "20240901T090000Z", "Planning", ""),
+        ]);
+        let params = FilterParams {
+            source: None,
+            from: Some("2024-06-01".to_string()),
+            to: Some("2024-06-30".to_string()),
+            q: None,
+            tz: None,
+        };
+        assert_eq!(filtered_uids(&calendar, params), vec!["a"]);
+    }
+
+    #[test]
+    fn filter_calendar_by_text_matches_summary_and_description_case_insensitively() {
+        let calendar = calendar_with_events(vec![
+            event("a", "work", "20240601T090000Z", "Daily Standup", ""),
+            event(
+                "b",
+                "work",
+                "20240601T100000Z",
+                "Planning",
+                "weekly STANDUP recap",
+            ),
+            event("c", "work", "20240601T110000Z", "Lunch", ""),
+        ]);
+        let params = FilterParams {
+            source: None,
+            from: None,
+            to: None,
+            q: Some("standup".to_string()),
+            tz: None,
+        };
+        let mut uids = filtered_uids(&calendar, params);
+        uids.sort();
+        assert_eq!(uids, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn filter_calendar_does_not_mutate_the_source_calendar() {
+        let calendar =
+            calendar_with_events(vec![event("a", "work", "20240601T090000Z", "Standup", "")]);
+        let params = FilterParams {
+            source: None,
+            from: None,
+            to: None,
+            q: None,
+            tz: Some("America/New_York".to_string()),
+        };
+        let _ = filter_calendar(&calendar, &params, Tz::UTC);
+
+        let original_dtstart = match &calendar.components[0] {
+            CalendarComponent::Event(event) => event.properties().get("DTSTART").unwrap().value(),
+            _ => panic!("expected a VEVENT"),
+        };
+        assert_eq!(original_dtstart, "20240601T090000Z");
+    }
+
+    fn rule(match_mode: MatchMode, conditions: Vec<Condition>, actions: Vec<Action>) -> Rule {
+        Rule {
+            name: "test rule".to_string(),
+            match_mode,
+            conditions,
+            actions,
+        }
+    }
+
+    fn condition(field: &str, op: ConditionOp, value: &str) -> Condition {
+        Condition {
+            field: field.to_string(),
+            op,
+            value: value.to_string(),
+        }
+    }
+
+    fn set_action(field: &str, value: &str) -> Action {
+        Action {
+            field: field.to_string(),
+            op: ActionOp::Set,
+            value: value.to_string(),
+            pattern: String::new(),
+            with: String::new(),
+        }
+    }
+
+    #[test]
+    fn condition_matches_every_op() {
+        let event = event(
+            "a",
+            "work",
+            "20240601T090000Z",
+            "Daily Standup",
+            "team sync",
+        );
+        let cache = RegexCache::new();
+
+        assert!(condition_matches(
+            &event,
+            &condition("SUMMARY", ConditionOp::Contains, "Stand"),
+            &cache
+        ));
+        assert!(condition_matches(
+            &event,
+            &condition("SUMMARY", ConditionOp::NotContains, "Lunch"),
+            &cache
+        ));
+        assert!(condition_matches(
+            &event,
+            &condition("SUMMARY", ConditionOp::Equals, "Daily Standup"),
+            &cache
+        ));
+        assert!(condition_matches(
+            &event,
+            &condition("SUMMARY", ConditionOp::StartsWith, "Daily"),
+            &cache
+        ));
+        assert!(condition_matches(
+            &event,
+            &condition("SUMMARY", ConditionOp::EndsWith, "Standup"),
+            &cache
+        ));
+
+        let rules = vec![rule(
+            MatchMode::All,
+            vec![condition("SUMMARY", ConditionOp::Regex, "^Daily .+up$")],
+            vec![],
+        )];
+        let regex_cache = build_regex_cache(&rules);
+        assert!(condition_matches(
+            &event,
+            &rules[0].conditions[0],
+            &regex_cache
+        ));
+    }
+
+    #[test]
+    fn apply_rules_match_mode_all_requires_every_condition() {
+        let mut event = event("a", "work", "20240601T090000Z", "Daily Standup", "");
+        let rules = vec![rule(
+            MatchMode::All,
+            vec![
+                condition("SUMMARY", ConditionOp::Contains, "Standup"),
+                condition("SUMMARY", ConditionOp::Contains, "Nope"),
+            ],
+            vec![set_action("LOCATION", "Room 1")],
+        )];
+        apply_rules(&mut event, &rules, &RegexCache::new());
+        assert_eq!(event.property_value("LOCATION"), None);
+    }
+
+    #[test]
+    fn apply_rules_match_mode_any_requires_one_condition() {
+        let mut event = event("a", "work", "20240601T090000Z", "Daily Standup", "");
+        let rules = vec![rule(
+            MatchMode::Any,
+            vec![
+                condition("SUMMARY", ConditionOp::Contains, "Standup"),
+                condition("SUMMARY", ConditionOp::Contains, "Nope"),
+            ],
+            vec![set_action("LOCATION", "Room 1")],
+        )];
+        apply_rules(&mut event, &rules, &RegexCache::new());
+        assert_eq!(event.property_value("LOCATION"), Some("Room 1"));
+    }
+
+    #[test]
+    fn apply_rules_prepend_append_and_delete() {
+        let mut event = event("a", "work", "20240601T090000Z", "Standup", "");
+        let rules = vec![rule(
+            MatchMode::All,
+            vec![condition("SUMMARY", ConditionOp::Contains, "Standup")],
+            vec![
+                Action {
+                    field: "SUMMARY".to_string(),
+                    op: ActionOp::Prepend,
+                    value: "[Team] ".to_string(),
+                    pattern: String::new(),
+                    with: String::new(),
+                },
+                Action {
+                    field: "SUMMARY".to_string(),
+                    op: ActionOp::Append,
+                    value: " (daily)".to_string(),
+                    pattern: String::new(),
+                    with: String::new(),
+                },
+                Action {
+                    field: "DESCRIPTION".to_string(),
+                    op: ActionOp::Delete,
+                    value: String::new(),
+                    pattern: String::new(),
+                    with: String::new(),
+                },
+            ],
+        )];
+        apply_rules(&mut event, &rules, &RegexCache::new());
+        assert_eq!(
+            event.property_value("SUMMARY"),
+            Some("[Team] Standup (daily)")
+        );
+        assert_eq!(event.property_value("DESCRIPTION"), None);
+    }
+
+    #[test]
+    fn apply_rules_replace_uses_regex_substitution() {
+        let mut event = event("a", "work", "20240601T090000Z", "Standup #123", "");
+        let rules = vec![rule(
+            MatchMode::All,
+            vec![condition("SUMMARY", ConditionOp::Contains, "Standup")],
+            vec![Action {
+                field: "SUMMARY".to_string(),
+                op: ActionOp::Replace,
+                value: String::new(),
+                pattern: r"#\d+".to_string(),
+                with: "[redacted]".to_string(),
+            }],
+        )];
+        apply_rules(&mut event, &rules, &build_regex_cache(&rules));
+        assert_eq!(event.property_value("SUMMARY"), Some("Standup [redacted]"));
+    }
+
+    #[test]
+    fn apply_rules_drop_signals_caller_to_discard_the_event() {
+        let mut event = event("a", "work", "20240601T090000Z", "Standup", "");
+        let rules = vec![rule(
+            MatchMode::All,
+            vec![condition("SUMMARY", ConditionOp::Contains, "Standup")],
+            vec![Action {
+                field: String::new(),
+                op: ActionOp::Drop,
+                value: String::new(),
+                pattern: String::new(),
+                with: String::new(),
+            }],
+        )];
+        assert!(apply_rules(&mut event, &rules, &RegexCache::new()));
+    }
+}